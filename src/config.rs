@@ -3,6 +3,8 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 use cargo_metadata::MetadataCommand;
 use clap::{App, AppSettings, Arg, ArgMatches};
 use semver::{Identifier, SemVerError, Version};
+use std::collections::HashMap;
+use std::fs;
 use std::path::PathBuf;
 use std::str::FromStr;
 
@@ -84,48 +86,134 @@ fn build_cli_parser<'a, 'b>() -> App<'a, 'b> {
                 .long("ignore-lockfile")
                 .help("Don't update Cargo.lock")
         )
+        .arg(
+            Arg::with_name("package")
+                .short("P")
+                .long("package")
+                .value_name("NAME")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Bump only the named workspace member (repeatable)"),
+        )
+        .arg(
+            Arg::with_name("all")
+                .long("all")
+                .alias("workspace")
+                .help("Bump every member of the workspace"),
+        )
+        .arg(
+            Arg::with_name("semver-compat")
+                .long("semver-compat")
+                .help("Interpret major/minor/patch by Cargo's 0.x caret rules"),
+        )
+        .arg(
+            Arg::with_name("pre-release-bump")
+                .long("pre-release-bump")
+                .help("Increment the last numeric pre-release identifier, e.g. beta.2 -> beta.3"),
+        )
+        .arg(
+            Arg::with_name("release")
+                .long("release")
+                .alias("finalize")
+                .help("Strip the pre-release and build segments, e.g. 1.4.0-beta.3 -> 1.4.0"),
+        )
+        .arg(
+            Arg::with_name("force")
+                .short("f")
+                .long("force")
+                .help("Skip the dirty-tree and duplicate-tag checks before tagging"),
+        )
+        .arg(
+            Arg::with_name("dist")
+                .long("dist")
+                .help("Package the crate sources into a NAME-VERSION.tar.gz after bumping"),
+        )
+        .arg(
+            Arg::with_name("dist-include")
+                .long("dist-include")
+                .value_name("PATH")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Extra file (crate-relative path) to include in the --dist archive (repeatable)"),
+        )
+}
+
+/// A single crate in the (possibly one-member) workspace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub name: String,
+    pub manifest: PathBuf,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Config {
     pub version_modifier: VersionModifier,
     pub manifest: PathBuf,
+    /// Members selected for bumping.
+    pub members: Vec<Member>,
+    /// Every member of the workspace, used to propagate dependency requirements.
+    pub workspace_members: Vec<Member>,
     pub git_tag: bool,
     pub run_build: bool,
     pub prefix: String,
     pub ignore_lockfile: bool,
+    /// Skip the git pre-flight checks before tagging.
+    pub force: bool,
+    /// Package the crate sources into a tarball after bumping.
+    pub dist: bool,
+    /// Extra crate-relative file paths to include in the `--dist` archive, on
+    /// top of the defaults.
+    pub dist_include: Vec<String>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         let mut metadata_cmd = MetadataCommand::new();
         let metadata = metadata_cmd.exec().expect("get cargo metadata");
-        let manifest = metadata[metadata
-            .workspace_members
-            .first()
-            .expect("get workspace members")]
-        .manifest_path
-        .to_owned();
+        let member = Member {
+            name: metadata[metadata
+                .workspace_members
+                .first()
+                .expect("get workspace members")]
+            .name
+            .clone(),
+            manifest: metadata[metadata
+                .workspace_members
+                .first()
+                .expect("get workspace members")]
+            .manifest_path
+            .to_owned(),
+        };
         let version_modifier = VersionModifier {
             mod_type: ModifierType::Patch,
             build_metadata: None,
             pre_release: None,
+            semver_compat: false,
+            pre_release_bump: false,
+            finalize: false,
         };
 
         Config {
             version_modifier,
-            manifest,
+            manifest: member.manifest.clone(),
+            members: vec![member.clone()],
+            workspace_members: vec![member],
             git_tag: false,
             run_build: false,
             prefix: "".into(),
             ignore_lockfile: false,
+            force: false,
+            dist: false,
+            dist_include: Vec::new(),
         }
     }
 }
 
 impl Config {
     fn from_matches(matches: ArgMatches) -> Config {
-        let mod_type = ModifierType::from_str(matches.value_of("VERSION").unwrap_or("patch"))
+        let mut mod_type = ModifierType::from_str(matches.value_of("VERSION").unwrap_or("patch"))
             .expect("Invalid semver version, expected version or major, minor, patch");
         let build_metadata = matches.value_of("build-metadata").map(parse_identifiers);
         let pre_release = matches.value_of("pre-release").map(parse_identifiers);
@@ -139,29 +227,351 @@ impl Config {
             None => "".to_string(),
         };
         let ignore_lockfile = matches.is_present("ignore-lockfile");
+        let force = matches.is_present("force");
+        let dist = matches.is_present("dist");
+        let dist_include: Vec<String> = matches
+            .values_of("dist-include")
+            .map(|paths| paths.map(String::from).collect())
+            .unwrap_or_default();
+        let semver_compat = matches.is_present("semver-compat");
+        let pre_release_bump = matches.is_present("pre-release-bump");
+        let finalize = matches.is_present("release");
+        // A bare `--release`/`--pre-release-bump` should act on the current
+        // version alone; without an explicit level, suppress the default
+        // `patch` bump that would otherwise also fire.
+        if matches.value_of("VERSION").is_none() && (finalize || pre_release_bump) {
+            mod_type = ModifierType::Keep;
+        }
         let mut metadata_cmd = MetadataCommand::new();
         if let Some(path) = matches.value_of("manifest-path") {
             metadata_cmd.manifest_path(path);
         }
         let metadata = metadata_cmd.exec().expect("get cargo metadata");
-        if metadata.workspace_members.len() == 1 {
-            Config {
-                version_modifier: VersionModifier {
-                    mod_type,
-                    build_metadata,
-                    pre_release,
-                },
-                manifest: metadata[&metadata.workspace_members[0]]
-                    .manifest_path
-                    .clone(),
-                git_tag,
-                run_build,
-                prefix,
-                ignore_lockfile,
-            }
+
+        let workspace_members: Vec<Member> = metadata
+            .workspace_members
+            .iter()
+            .map(|id| Member {
+                name: metadata[id].name.clone(),
+                manifest: metadata[id].manifest_path.clone(),
+            })
+            .collect();
+
+        let selected: Vec<String> = matches
+            .values_of("package")
+            .map(|names| names.map(String::from).collect())
+            .unwrap_or_default();
+        let all = matches.is_present("all");
+
+        let members: Vec<Member> = if all {
+            workspace_members.clone()
+        } else if !selected.is_empty() {
+            selected
+                .iter()
+                .map(|name| {
+                    workspace_members
+                        .iter()
+                        .find(|m| &m.name == name)
+                        .unwrap_or_else(|| {
+                            panic!("No workspace member named '{}'", name)
+                        })
+                        .clone()
+                })
+                .collect()
+        } else if workspace_members.len() == 1 {
+            workspace_members.clone()
         } else {
-            panic!("Workspaces are not supported yet.");
+            panic!(
+                "This is a workspace; pass --all or --package <NAME> to select which crates to bump."
+            );
+        };
+
+        Config {
+            version_modifier: VersionModifier {
+                mod_type,
+                build_metadata,
+                pre_release,
+                semver_compat,
+                pre_release_bump,
+                finalize,
+            },
+            manifest: members[0].manifest.clone(),
+            members,
+            workspace_members,
+            git_tag,
+            run_build,
+            prefix,
+            ignore_lockfile,
+            force,
+            dist,
+            dist_include,
+        }
+    }
+}
+
+/// Rewrite the intra-workspace dependency requirements of every member's
+/// manifest so they match the freshly-bumped crate versions.
+///
+/// `bumps` maps a bumped crate's package name to its new [`Version`]. For each
+/// member manifest, any `[dependencies]`/`[dev-dependencies]`/
+/// `[build-dependencies]` entry whose key (or explicit `package = "..."`) names
+/// a bumped crate has its `version` requirement rewritten, preserving any
+/// leading `^`/`~`/`=` operator.
+pub fn propagate_dependencies(members: &[Member], bumps: &HashMap<String, Version>) {
+    for member in members {
+        let contents = fs::read_to_string(&member.manifest)
+            .unwrap_or_else(|e| panic!("read {}: {}", member.manifest.display(), e));
+        let rewritten = rewrite_dependency_requirements(&contents, bumps);
+        if rewritten != contents {
+            fs::write(&member.manifest, rewritten)
+                .unwrap_or_else(|e| panic!("write {}: {}", member.manifest.display(), e));
+        }
+    }
+}
+
+/// Parse the `version` declared in a manifest's `[package]` section.
+pub fn parse_package_version(contents: &str) -> Option<Version> {
+    let mut in_package = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_package = trimmed == "[package]";
+            continue;
+        }
+        if in_package {
+            let eq = match line.find('=') {
+                Some(eq) => eq,
+                None => continue,
+            };
+            if line[..eq].trim() == "version" {
+                let (req, _) = quoted_span(&line[eq + 1..])?;
+                let op = requirement_operator(req);
+                return Version::parse(req.trim().trim_start_matches(op)).ok();
+            }
+        }
+    }
+    None
+}
+
+/// Rewrite the `version` in a manifest's `[package]` section to `version`,
+/// leaving the rest of the file untouched.
+pub fn set_package_version(contents: &str, version: &Version) -> String {
+    let mut in_package = false;
+    let mut done = false;
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_package = trimmed == "[package]";
+            lines.push(line.to_string());
+            continue;
+        }
+        if in_package && !done {
+            if let Some(rewritten) = rewrite_version_field(line, version) {
+                lines.push(rewritten);
+                done = true;
+                continue;
+            }
+        }
+        lines.push(line.to_string());
+    }
+
+    let mut out = lines.join("\n");
+    if contents.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+fn rewrite_dependency_requirements(contents: &str, bumps: &HashMap<String, Version>) -> String {
+    let mut in_deps = false;
+    let mut lines: Vec<String> = Vec::new();
+    // The crate named by the current inline dependency, e.g. `[dependencies.foo]`.
+    let mut table_crate: Option<String> = None;
+
+    let all: Vec<&str> = contents.lines().collect();
+    for (i, line) in all.iter().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_deps = is_dependency_header(trimmed);
+            // A `package = "..."` line inside the table names the real crate.
+            table_crate = dependency_table_crate(trimmed).map(|key| {
+                all[i + 1..]
+                    .iter()
+                    .take_while(|l| !l.trim().starts_with('['))
+                    .find_map(|l| {
+                        let eq = l.find('=')?;
+                        if l[..eq].trim() != "package" {
+                            return None;
+                        }
+                        quoted_span(&l[eq + 1..]).map(|(name, _)| name.to_string())
+                    })
+                    .unwrap_or(key)
+            });
+            lines.push(line.to_string());
+            continue;
+        }
+
+        if in_deps {
+            if let Some(name) = &table_crate {
+                // `[dependencies.foo]` form: rewrite a bare `version = "..."` line.
+                if let Some(version) = bumps.get(name) {
+                    if let Some(rewritten) = rewrite_version_field(line, version) {
+                        lines.push(rewritten);
+                        continue;
+                    }
+                }
+            } else if let Some((key, pkg)) = dependency_key(trimmed) {
+                // `foo = { version = "..." }` or `foo = "..."` form.
+                let name = pkg.unwrap_or(key);
+                if let Some(version) = bumps.get(&name) {
+                    if let Some(rewritten) = rewrite_inline_requirement(line, version) {
+                        lines.push(rewritten);
+                        continue;
+                    }
+                }
+            }
         }
+
+        lines.push(line.to_string());
+    }
+
+    let mut out = lines.join("\n");
+    if contents.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+fn is_dependency_header(header: &str) -> bool {
+    let inner = header.trim_start_matches('[').trim_end_matches(']');
+    dependency_section(inner).is_some()
+}
+
+/// Return the dependency-section part of a table header, stripping an optional
+/// `target.<spec>.` prefix so that `[target.'cfg(unix)'.dependencies]` and the
+/// like are treated as dependency sections. Returns `None` for non-dependency
+/// tables such as `[package]`.
+fn dependency_section(inner: &str) -> Option<&str> {
+    for section in &["dependencies", "dev-dependencies", "build-dependencies"] {
+        if inner == *section || inner.starts_with(&format!("{}.", section)) {
+            return Some(inner);
+        }
+        // `target.<spec>.dependencies[...]`, where `<spec>` may itself contain
+        // dots inside quotes (e.g. `'cfg(unix)'`).
+        if inner.starts_with("target.") {
+            if let Some(idx) = inner.find(&format!(".{}", section)) {
+                return Some(&inner[idx + 1..]);
+            }
+        }
+    }
+    None
+}
+
+fn dependency_table_crate(header: &str) -> Option<String> {
+    let inner = header.trim_start_matches('[').trim_end_matches(']');
+    let section = dependency_section(inner)?;
+    section
+        .strip_prefix("dependencies.")
+        .or_else(|| section.strip_prefix("dev-dependencies."))
+        .or_else(|| section.strip_prefix("build-dependencies."))
+        .map(|name| name.to_string())
+}
+
+/// Split a `key = value` dependency line, returning `(key, explicit package)`.
+fn dependency_key(line: &str) -> Option<(String, Option<String>)> {
+    let eq = line.find('=')?;
+    let key = line[..eq].trim().trim_matches('"').to_string();
+    if key.is_empty() {
+        return None;
+    }
+    let value = line[eq + 1..].trim();
+    let package = value
+        .find("package")
+        .and_then(|_| extract_quoted_after(value, "package"));
+    Some((key, package))
+}
+
+fn extract_quoted_after(value: &str, key: &str) -> Option<String> {
+    let idx = value.find(key)? + key.len();
+    let rest = value[idx..].trim_start().strip_prefix('=')?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+    let end = rest.find('"')?;
+    Some(rest[..end].to_string())
+}
+
+/// Rewrite a bare `version = "<req>"` line, preserving indentation and operator.
+fn rewrite_version_field(line: &str, version: &Version) -> Option<String> {
+    let eq = line.find('=')?;
+    if line[..eq].trim() != "version" {
+        return None;
+    }
+    let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+    let op = requirement_operator(&line[eq + 1..]);
+    Some(format!("{}version = \"{}{}\"", indent, op, version))
+}
+
+/// Rewrite the `version` requirement inside an inline dependency line such as
+/// `foo = "1.2.3"` or `foo = { version = "1.2.3", features = [...] }`.
+fn rewrite_inline_requirement(line: &str, version: &Version) -> Option<String> {
+    let eq = line.find('=')?;
+    let value = line[eq + 1..].trim_start();
+    if value.starts_with('{') {
+        // Inline table: only rewrite an explicit `version = "..."` key. A
+        // version-less table such as `foo = { path = "../foo" }` carries no
+        // requirement to bump, and must be left untouched — rewriting it as a
+        // bare string would destroy the table and drop the path.
+        let pos = line.find("version")?;
+        let after = &line[pos + "version".len()..];
+        let veq = after.find('=')?;
+        let (req, tail) = quoted_span(&after[veq + 1..])?;
+        let op = requirement_operator(req);
+        let prefix = &line[..pos + "version".len() + veq + 1];
+        let leading: String = after[veq + 1..]
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect();
+        return Some(format!(
+            "{}{}\"{}{}\"{}",
+            prefix, leading, op, version, tail
+        ));
+    }
+    // Bare `foo = "1.2.3"` form.
+    let (req, tail) = quoted_span(&line[eq + 1..])?;
+    let op = requirement_operator(req);
+    let leading: String = line[eq + 1..]
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+    Some(format!(
+        "{}={}\"{}{}\"{}",
+        &line[..eq],
+        leading,
+        op,
+        version,
+        tail
+    ))
+}
+
+/// Return the contents of the first `"..."` span plus whatever follows it.
+fn quoted_span(value: &str) -> Option<(&str, &str)> {
+    let start = value.find('"')? + 1;
+    let end = value[start..].find('"')? + start;
+    Some((&value[start..end], &value[end + 1..]))
+}
+
+/// Preserve a leading `^`/`~`/`=` operator from an existing requirement string.
+fn requirement_operator(req: &str) -> &'static str {
+    let req = req.trim().trim_start_matches('"');
+    if req.starts_with('^') {
+        "^"
+    } else if req.starts_with('~') {
+        "~"
+    } else if req.starts_with('=') {
+        "="
+    } else {
+        ""
     }
 }
 
@@ -184,6 +594,10 @@ pub enum ModifierType {
     Major,
     Minor,
     Patch,
+    /// Leave the core `major.minor.patch` untouched. Used when only a
+    /// pre-release operation (`--release`/`--pre-release-bump`) is requested
+    /// without an explicit level, so the default `patch` bump is suppressed.
+    Keep,
 }
 
 impl FromStr for ModifierType {
@@ -203,6 +617,12 @@ pub struct VersionModifier {
     pub mod_type: ModifierType,
     pub build_metadata: Option<Vec<Identifier>>,
     pub pre_release: Option<Vec<Identifier>>,
+    /// Interpret `Major`/`Minor`/`Patch` by Cargo's 0.x caret rules.
+    pub semver_compat: bool,
+    /// Increment the trailing numeric pre-release identifier.
+    pub pre_release_bump: bool,
+    /// Strip the pre-release and build segments to promote a release.
+    pub finalize: bool,
 }
 
 impl VersionModifier {
@@ -216,6 +636,9 @@ impl VersionModifier {
             mod_type,
             build_metadata: build_metadata.map(parse_identifiers),
             pre_release: pre_release.map(parse_identifiers),
+            semver_compat: false,
+            pre_release_bump: false,
+            finalize: false,
         }
     }
 
@@ -225,6 +648,9 @@ impl VersionModifier {
             mod_type,
             build_metadata: None,
             pre_release: None,
+            semver_compat: false,
+            pre_release_bump: false,
+            finalize: false,
         }
     }
 }
@@ -273,6 +699,9 @@ mod tests {
             mod_type: ModifierType::Major,
             build_metadata: Some(vec![Identifier::Numeric(1999)]),
             pre_release: None,
+            semver_compat: false,
+            pre_release_bump: false,
+            finalize: false,
         };
         test_config(input, version_mod);
     }
@@ -284,7 +713,113 @@ mod tests {
             mod_type: ModifierType::Replace(Version::parse("2.0.0").unwrap()),
             build_metadata: None,
             pre_release: Some(vec![Identifier::AlphaNumeric(String::from("beta"))]),
+            semver_compat: false,
+            pre_release_bump: false,
+            finalize: false,
         };
         test_config(input, version_mod);
     }
+
+    fn version_from_cli(input: Vec<&str>, current: &str) -> Version {
+        let parser = build_cli_parser();
+        let matches = parser.get_matches_from_safe(input).unwrap();
+        let config = Config::from_matches(matches);
+        let mut version = Version::parse(current).unwrap();
+        ::version::update_version(&mut version, config.version_modifier);
+        version
+    }
+
+    #[test]
+    fn finalize_via_cli_promotes_without_level_bump() {
+        let version = version_from_cli(vec!["cargo-bump", "bump", "--release"], "1.4.0-beta.3");
+        assert_eq!(version, Version::parse("1.4.0").unwrap());
+    }
+
+    #[test]
+    fn pre_release_bump_via_cli_keeps_core() {
+        let version =
+            version_from_cli(vec!["cargo-bump", "bump", "--pre-release-bump"], "1.4.0-beta.2");
+        assert_eq!(version, Version::parse("1.4.0-beta.3").unwrap());
+    }
+
+    fn bumps(name: &str, version: &str) -> HashMap<String, Version> {
+        let mut map = HashMap::new();
+        map.insert(name.to_string(), Version::parse(version).unwrap());
+        map
+    }
+
+    #[test]
+    fn rewrite_inline_table_dependency() {
+        let manifest = "[dependencies]\nfoo = { version = \"^0.1.0\", features = [\"a\"] }\n";
+        let out = rewrite_dependency_requirements(manifest, &bumps("foo", "0.2.0"));
+        assert_eq!(
+            out,
+            "[dependencies]\nfoo = { version = \"^0.2.0\", features = [\"a\"] }\n"
+        );
+    }
+
+    #[test]
+    fn rewrite_bare_string_dependency() {
+        let manifest = "[dev-dependencies]\nfoo = \"1.2.3\"\n";
+        let out = rewrite_dependency_requirements(manifest, &bumps("foo", "1.3.0"));
+        assert_eq!(out, "[dev-dependencies]\nfoo = \"1.3.0\"\n");
+    }
+
+    #[test]
+    fn rewrite_dotted_dependency_table() {
+        let manifest = "[dependencies.foo]\nversion = \"=0.1.0\"\npath = \"../foo\"\n";
+        let out = rewrite_dependency_requirements(manifest, &bumps("foo", "0.1.1"));
+        assert_eq!(
+            out,
+            "[dependencies.foo]\nversion = \"=0.1.1\"\npath = \"../foo\"\n"
+        );
+    }
+
+    #[test]
+    fn leaves_unrelated_dependencies_untouched() {
+        let manifest = "[dependencies]\nbar = \"1.0.0\"\n";
+        let out = rewrite_dependency_requirements(manifest, &bumps("foo", "2.0.0"));
+        assert_eq!(out, manifest);
+    }
+
+    #[test]
+    fn leaves_version_less_path_dependency_untouched() {
+        let manifest = "[dependencies]\nfoo = { path = \"../foo\" }\n";
+        let out = rewrite_dependency_requirements(manifest, &bumps("foo", "0.2.0"));
+        assert_eq!(out, manifest);
+    }
+
+    #[test]
+    fn rewrites_path_dependency_with_version() {
+        let manifest = "[dependencies]\nfoo = { path = \"../foo\", version = \"0.1.0\" }\n";
+        let out = rewrite_dependency_requirements(manifest, &bumps("foo", "0.2.0"));
+        assert_eq!(
+            out,
+            "[dependencies]\nfoo = { path = \"../foo\", version = \"0.2.0\" }\n"
+        );
+    }
+
+    #[test]
+    fn reads_and_writes_package_version() {
+        let manifest = "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n\n[dependencies]\nbar = \"1.0.0\"\n";
+        assert_eq!(
+            parse_package_version(manifest),
+            Some(Version::parse("0.1.0").unwrap())
+        );
+        let out = set_package_version(manifest, &Version::parse("0.2.0").unwrap());
+        assert_eq!(
+            out,
+            "[package]\nname = \"foo\"\nversion = \"0.2.0\"\n\n[dependencies]\nbar = \"1.0.0\"\n"
+        );
+    }
+
+    #[test]
+    fn rewrites_target_specific_dependency() {
+        let manifest = "[target.'cfg(unix)'.dependencies]\nfoo = \"0.1.0\"\n";
+        let out = rewrite_dependency_requirements(manifest, &bumps("foo", "0.2.0"));
+        assert_eq!(
+            out,
+            "[target.'cfg(unix)'.dependencies]\nfoo = \"0.2.0\"\n"
+        );
+    }
 }
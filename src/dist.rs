@@ -0,0 +1,70 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use semver::Version;
+use tar::Builder;
+
+/// Files bundled into every `--dist` archive when present; `--dist-include`
+/// adds to this list and cannot remove from it.
+const DEFAULT_INCLUDE: &[&str] = &["README.md", "LICENSE"];
+
+/// Package the crate sources into a `NAME-VERSION.tar.gz`.
+///
+/// The archive is derived from the freshly-written `package.name` and the new
+/// [`Version`], contains `Cargo.toml` and the `src` tree plus `include` (the
+/// defaults [`DEFAULT_INCLUDE`] extended by the crate-relative paths passed to
+/// `--dist-include`), and is written into `target_dir`. Returns the path to the
+/// written archive.
+pub fn build_dist(
+    name: &str,
+    version: &Version,
+    crate_dir: &Path,
+    target_dir: &Path,
+    include: &[String],
+) -> PathBuf {
+    let stem = format!("{}-{}", name, version);
+    let archive = target_dir.join(format!("{}.tar.gz", stem));
+
+    let file = File::create(&archive)
+        .unwrap_or_else(|e| panic!("create {}: {}", archive.display(), e));
+    let encoder = GzEncoder::new(file, Compression::default());
+    let mut builder = Builder::new(encoder);
+
+    // Always ship the manifest and the sources under the archive's top dir.
+    append_path(&mut builder, crate_dir, "Cargo.toml", &stem);
+    append_dir(&mut builder, crate_dir, "src", &stem);
+
+    for entry in DEFAULT_INCLUDE
+        .iter()
+        .map(|s| s.to_string())
+        .chain(include.iter().cloned())
+    {
+        if crate_dir.join(&entry).exists() {
+            append_path(&mut builder, crate_dir, &entry, &stem);
+        }
+    }
+
+    builder
+        .into_inner()
+        .expect("finish tar builder")
+        .finish()
+        .expect("finish gzip encoder");
+
+    archive
+}
+
+fn append_path(builder: &mut Builder<GzEncoder<File>>, crate_dir: &Path, rel: &str, stem: &str) {
+    let path = crate_dir.join(rel);
+    builder
+        .append_path_with_name(&path, format!("{}/{}", stem, rel))
+        .unwrap_or_else(|e| panic!("add {} to archive: {}", path.display(), e));
+}
+
+fn append_dir(builder: &mut Builder<GzEncoder<File>>, crate_dir: &Path, rel: &str, stem: &str) {
+    let path = crate_dir.join(rel);
+    builder
+        .append_dir_all(format!("{}/{}", stem, rel), &path)
+        .unwrap_or_else(|e| panic!("add {} to archive: {}", path.display(), e));
+}
@@ -0,0 +1,87 @@
+use std::process::Command;
+
+/// Verify that the repository is in a sane state before tagging a release.
+///
+/// Unless `force` is set, this refuses to proceed when the working tree is
+/// dirty or when a tag named `tag` already exists, printing a clear error and
+/// exiting non-zero. `force` skips every check, mirroring the projectr xtask
+/// `version(force)` behaviour.
+pub fn verify_git_state(tag: &str, force: bool) {
+    if force {
+        return;
+    }
+
+    if !git_available() {
+        eprintln!("error: `git` is not available on the PATH");
+        std::process::exit(1);
+    }
+
+    if working_tree_dirty() {
+        eprintln!("error: working tree is dirty; commit or stash changes, or pass --force");
+        std::process::exit(1);
+    }
+
+    if tag_exists(tag) {
+        eprintln!("error: git tag '{}' already exists; pass --force to override", tag);
+        std::process::exit(1);
+    }
+}
+
+fn git_available() -> bool {
+    Command::new("git")
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn working_tree_dirty() -> bool {
+    let output = Command::new("git")
+        .args(&["status", "--porcelain"])
+        .output()
+        .expect("run git status");
+    status_is_dirty(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn tag_exists(tag: &str) -> bool {
+    let output = Command::new("git")
+        .args(&["tag", "--list", tag])
+        .output()
+        .expect("run git tag --list");
+    tag_listed(&String::from_utf8_lossy(&output.stdout), tag)
+}
+
+/// `true` when `git status --porcelain` reports any tracked or untracked change.
+fn status_is_dirty(porcelain: &str) -> bool {
+    porcelain.lines().any(|line| !line.trim().is_empty())
+}
+
+/// `true` when `tag` appears in the output of `git tag --list <tag>`.
+fn tag_listed(list_output: &str, tag: &str) -> bool {
+    list_output.lines().any(|line| line.trim() == tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_tree_is_not_dirty() {
+        assert!(!status_is_dirty(""));
+        assert!(!status_is_dirty("\n"));
+    }
+
+    #[test]
+    fn modified_or_untracked_tree_is_dirty() {
+        assert!(status_is_dirty(" M src/config.rs\n"));
+        assert!(status_is_dirty("?? src/new.rs\n"));
+    }
+
+    #[test]
+    fn tag_presence_is_detected_exactly() {
+        assert!(tag_listed("v1.2.3\n", "v1.2.3"));
+        assert!(!tag_listed("", "v1.2.3"));
+        // A prefix match must not count as the tag existing.
+        assert!(!tag_listed("v1.2.30\n", "v1.2.3"));
+    }
+}
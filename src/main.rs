@@ -0,0 +1,120 @@
+extern crate cargo_metadata;
+extern crate clap;
+extern crate flate2;
+extern crate semver;
+extern crate tar;
+
+mod config;
+mod dist;
+mod git;
+mod version;
+
+use std::collections::HashMap;
+use std::fs;
+use std::process::Command;
+
+use semver::Version;
+
+use config::{get_config, Config};
+use version::update_version;
+
+fn main() {
+    run(get_config());
+}
+
+fn run(config: Config) {
+    // Compute each selected member's new version up front, without touching any
+    // file yet, so we can vet the repository state before making edits.
+    let mut bumps: HashMap<String, Version> = HashMap::new();
+    let mut planned: Vec<(&config::Member, Version)> = Vec::new();
+    for member in &config.members {
+        let contents = fs::read_to_string(&member.manifest)
+            .unwrap_or_else(|e| panic!("read {}: {}", member.manifest.display(), e));
+        let mut new_version = config::parse_package_version(&contents)
+            .unwrap_or_else(|| panic!("no [package] version in {}", member.manifest.display()));
+        update_version(&mut new_version, config.version_modifier.clone());
+        bumps.insert(member.name.clone(), new_version.clone());
+        planned.push((member, new_version));
+    }
+
+    // The primary (first-selected) member names the release tag and archive.
+    let primary = &config.members[0];
+    let new_version = bumps[&primary.name].clone();
+
+    // Pre-flight the git state *before* writing anything, so the dirty-tree
+    // check guards against pre-existing changes rather than our own edits.
+    let tag = format!("{}{}", config.prefix, new_version);
+    if config.git_tag {
+        git::verify_git_state(&tag, config.force);
+    }
+
+    // Now commit the planned versions to disk and keep every member's
+    // intra-workspace requirements consistent with them.
+    for (member, new_version) in &planned {
+        let contents = fs::read_to_string(&member.manifest)
+            .unwrap_or_else(|e| panic!("read {}: {}", member.manifest.display(), e));
+        let rewritten = config::set_package_version(&contents, new_version);
+        fs::write(&member.manifest, rewritten)
+            .unwrap_or_else(|e| panic!("write {}: {}", member.manifest.display(), e));
+        println!("{} => {}", member.name, new_version);
+    }
+    config::propagate_dependencies(&config.workspace_members, &bumps);
+
+    if config.run_build {
+        run_cargo_build(config.ignore_lockfile);
+    }
+
+    if config.git_tag {
+        git_commit_and_tag(&tag);
+    }
+
+    if config.dist {
+        let crate_dir = primary
+            .manifest
+            .parent()
+            .expect("manifest path has a parent directory");
+        let target_dir = crate_dir.join("target");
+        fs::create_dir_all(&target_dir)
+            .unwrap_or_else(|e| panic!("create {}: {}", target_dir.display(), e));
+        let archive = dist::build_dist(
+            &primary.name,
+            &new_version,
+            crate_dir,
+            &target_dir,
+            &config.dist_include,
+        );
+        println!("packaged {}", archive.display());
+    }
+}
+
+fn run_cargo_build(ignore_lockfile: bool) {
+    // A plain `cargo build` refreshes `Cargo.lock`; `--locked` forbids that, so
+    // `--ignore-lockfile` leaves the lockfile exactly as it was.
+    let mut command = Command::new("cargo");
+    command.arg("build");
+    if ignore_lockfile {
+        command.arg("--locked");
+    }
+    let status = command.status().expect("run cargo build");
+    if !status.success() {
+        eprintln!("error: `cargo build` failed");
+        std::process::exit(1);
+    }
+}
+
+fn git_commit_and_tag(tag: &str) {
+    run_git(&["add", "-A"]);
+    run_git(&["commit", "-m", tag]);
+    run_git(&["tag", tag]);
+}
+
+fn run_git(args: &[&str]) {
+    let status = Command::new("git")
+        .args(args)
+        .status()
+        .expect("run git");
+    if !status.success() {
+        eprintln!("error: `git {}` failed", args.join(" "));
+        std::process::exit(1);
+    }
+}
@@ -1,13 +1,22 @@
-use std::str::FromStr;
-
 use config::{ModifierType, VersionModifier};
-use semver::{Prerelease, Version};
+use semver::{Identifier, Version};
 
 pub fn update_version(version: &mut Version, modifier: VersionModifier) {
     match modifier.mod_type {
         ModifierType::Replace(v) => {
             *version = v;
         }
+        // Under Cargo's caret rules the left-most non-zero component is the
+        // compatibility boundary, so for `0.x` releases a breaking bump lands
+        // on `minor` and a feature bump on `patch`. The predicate is simply
+        // `major == 0`, mirroring cargo-smart-release's `is_pre_release_version`.
+        ModifierType::Major if modifier.semver_compat && version.major == 0 => {
+            version.minor += 1;
+            version.patch = 0;
+        }
+        ModifierType::Minor if modifier.semver_compat && version.major == 0 => {
+            version.patch += 1;
+        }
         ModifierType::Major => {
             version.major += 1;
             version.minor = 0;
@@ -20,12 +29,103 @@ pub fn update_version(version: &mut Version, modifier: VersionModifier) {
         ModifierType::Patch => {
             version.patch += 1;
         }
+        // Only a pre-release operation was requested; leave the core alone.
+        ModifierType::Keep => {}
     }
 
     if let Some(pre) = modifier.pre_release {
-        version.pre = Prerelease::from_str(&pre).unwrap();
+        version.pre = pre;
+    }
+    if modifier.pre_release_bump {
+        bump_pre_release(&mut version.pre);
     }
     if let Some(build) = modifier.build_metadata {
         version.build = build;
     }
+    if modifier.finalize {
+        version.pre.clear();
+        version.build.clear();
+    }
+}
+
+/// Increment the last numeric identifier of a pre-release segment, e.g.
+/// `beta.2` -> `beta.3` (and `rc.1.x` -> `rc.2.x`). When the segment has no
+/// numeric identifier at all (e.g. `beta`) a `.1` is appended, and an empty
+/// segment becomes `1`.
+fn bump_pre_release(pre: &mut Vec<Identifier>) {
+    match pre.iter_mut().rev().find_map(|id| match id {
+        Identifier::Numeric(n) => Some(n),
+        _ => None,
+    }) {
+        Some(n) => *n += 1,
+        None => pre.push(Identifier::Numeric(1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bump(version: &str, mod_type: ModifierType, semver_compat: bool) -> Version {
+        let mut version = Version::parse(version).unwrap();
+        let mut modifier = VersionModifier::from_mod_type(mod_type);
+        modifier.semver_compat = semver_compat;
+        update_version(&mut version, modifier);
+        version
+    }
+
+    #[test]
+    fn semver_compat_breaking_on_zero_major() {
+        assert_eq!(
+            bump("0.1.5", ModifierType::Major, true),
+            Version::parse("0.2.0").unwrap()
+        );
+    }
+
+    #[test]
+    fn semver_compat_feature_on_zero_major() {
+        assert_eq!(
+            bump("0.1.5", ModifierType::Minor, true),
+            Version::parse("0.1.6").unwrap()
+        );
+    }
+
+    #[test]
+    fn semver_compat_breaking_above_one() {
+        assert_eq!(
+            bump("1.2.3", ModifierType::Major, true),
+            Version::parse("2.0.0").unwrap()
+        );
+    }
+
+    #[test]
+    fn literal_levels_unaffected_by_default() {
+        assert_eq!(
+            bump("0.1.5", ModifierType::Major, false),
+            Version::parse("1.0.0").unwrap()
+        );
+    }
+
+    #[test]
+    fn pre_release_bump_increments_numeric() {
+        let mut pre = Version::parse("1.4.0-beta.2").unwrap().pre;
+        bump_pre_release(&mut pre);
+        assert_eq!(pre, Version::parse("1.4.0-beta.3").unwrap().pre);
+    }
+
+    #[test]
+    fn pre_release_bump_appends_when_non_numeric() {
+        let mut pre = Version::parse("1.4.0-beta").unwrap().pre;
+        bump_pre_release(&mut pre);
+        assert_eq!(pre, Version::parse("1.4.0-beta.1").unwrap().pre);
+    }
+
+    #[test]
+    fn finalize_strips_pre_release_and_build() {
+        let mut version = Version::parse("1.4.0-beta.3+build.7").unwrap();
+        let mut modifier = VersionModifier::from_mod_type(ModifierType::Replace(version.clone()));
+        modifier.finalize = true;
+        update_version(&mut version, modifier);
+        assert_eq!(version, Version::parse("1.4.0").unwrap());
+    }
 }